@@ -1,4 +1,6 @@
 use anyhow::{Context, Result};
+use geo::{Contains, Intersects};
+use geo_types::{Geometry, LineString, MultiLineString, MultiPoint, MultiPolygon, Point as GeoPoint, Polygon, Rect};
 use prost::Message;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
@@ -26,6 +28,32 @@ impl TileCoord {
     }
 }
 
+/// タイルローカルの正規化座標(u, v)をWGS84の経度緯度に変換する
+///
+/// `(u, v)`は`Extent::normalize`が返す0..1の範囲のタイル内座標
+pub fn tile_to_lonlat(tile: TileCoord, u: f64, v: f64) -> (f64, f64) {
+    let n = 2_f64.powi(tile.z as i32);
+    let fx = (tile.x as f64 + u) / n;
+    let fy = (tile.y as f64 + v) / n;
+    let lon = fx * 360.0 - 180.0;
+    let lat = (std::f64::consts::PI * (1.0 - 2.0 * fy)).sinh().atan().to_degrees();
+    (lon, lat)
+}
+
+/// WGS84の経度緯度を、指定タイル内のローカル整数座標（0..extent）に変換する
+///
+/// `tile_to_lonlat`の逆変換で、`json_to_mvt`によるエンコード時に使用する
+pub fn lonlat_to_tile_local(tile: TileCoord, lon: f64, lat: f64, extent: Extent) -> (i32, i32) {
+    let n = 2_f64.powi(tile.z as i32);
+    let fx = (lon + 180.0) / 360.0;
+    let lat_rad = lat.to_radians();
+    let fy = (1.0 - (lat_rad.tan() + 1.0 / lat_rad.cos()).ln() / std::f64::consts::PI) / 2.0;
+
+    let local_x = (fx * n - tile.x as f64) * extent.value() as f64;
+    let local_y = (fy * n - tile.y as f64) * extent.value() as f64;
+    (local_x.round() as i32, local_y.round() as i32)
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct Extent(u32);
 
@@ -49,6 +77,38 @@ impl Default for Extent {
     }
 }
 
+/// 出力座標の丸め桁数とf32narrowingを指定する
+///
+/// `precision`は小数点以下の桁数、`float32`を立てるとf32に狭めてシリアライズする
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct CoordFormat {
+    pub precision: Option<u32>,
+    pub float32: bool,
+}
+
+impl CoordFormat {
+    pub fn new(precision: Option<u32>, float32: bool) -> Self {
+        Self { precision, float32 }
+    }
+
+    /// 精度設定に従って値を丸め、`float32`が立っていればf32として出力する
+    pub fn apply(&self, value: f64) -> serde_json::Value {
+        let value = match self.precision {
+            Some(digits) => {
+                let factor = 10f64.powi(digits as i32);
+                (value * factor).round() / factor
+            }
+            None => value,
+        };
+
+        if self.float32 {
+            serde_json::json!(value as f32)
+        } else {
+            serde_json::json!(value)
+        }
+    }
+}
+
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub struct LatLon {
     pub lat: f64,
@@ -85,7 +145,7 @@ impl PredefinedLocation {
     }
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct GeoJsonFeature {
     #[serde(rename = "type")]
     pub type_: String,
@@ -93,29 +153,27 @@ pub struct GeoJsonFeature {
     pub id: Option<u64>,
     pub geometry: GeoJsonGeometry,
     pub properties: HashMap<String, serde_json::Value>,
+    pub layer: String,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct GeoJsonGeometry {
     #[serde(rename = "type")]
     pub type_: String,
     pub coordinates: serde_json::Value,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
-pub struct Layer {
-    pub name: String,
-    pub extent: u32,
-    pub version: u32,
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FeatureCollection {
+    #[serde(rename = "type")]
+    pub type_: String,
     pub features: Vec<GeoJsonFeature>,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
-pub struct TileData {
-    pub layers: Vec<Layer>,
-}
-
-/// MVTタイルをフェッチして、生のバイト列として返す
+/// MVTタイルをフェッチして、生のバイト列と`Content-Encoding`ヘッダーを返す
+///
+/// レスポンスボディは圧縮されている場合があるため、デコードは呼び出し側で
+/// `decode_tile_bytes`を使って行う
 ///
 /// # Arguments
 /// * `url` - MVTタイルのURL
@@ -123,7 +181,7 @@ pub struct TileData {
 ///
 /// # Errors
 /// HTTPリクエストが失敗した場合やレスポンスの読み取りに失敗した場合
-pub fn fetch_mvt(url: &str, headers: &[String]) -> Result<Vec<u8>> {
+pub fn fetch_mvt(url: &str, headers: &[String]) -> Result<(Vec<u8>, Option<String>)> {
     let client = reqwest::blocking::Client::new();
     let mut request = client.get(url);
 
@@ -138,12 +196,58 @@ pub fn fetch_mvt(url: &str, headers: &[String]) -> Result<Vec<u8>> {
         }
     }
 
-    let response = request
-        .send()
-        .context("Failed to fetch URL")?
-        .bytes()
-        .context("Failed to read response body")?;
-    Ok(response.to_vec())
+    let response = request.send().context("Failed to fetch URL")?;
+    let content_encoding = response
+        .headers()
+        .get(reqwest::header::CONTENT_ENCODING)
+        .and_then(|v| v.to_str().ok())
+        .map(str::to_string);
+    let body = response.bytes().context("Failed to read response body")?;
+    Ok((body.to_vec(), content_encoding))
+}
+
+/// バイト列が圧縮されているかを`Content-Encoding`ヘッダーとマジックバイトの両方で判定し、
+/// 必要であれば展開した生のMVT protobufバイト列を返す
+///
+/// # Arguments
+/// * `data` - HTTPレスポンスボディの生バイト列
+/// * `content_encoding` - `Content-Encoding`ヘッダーの値（存在する場合）
+///
+/// # Errors
+/// 圧縮ストリームとして不正な場合（gzipマジックバイトはあるがgzip本体が壊れている等）
+pub fn decode_tile_bytes(data: &[u8], content_encoding: Option<&str>) -> Result<Vec<u8>> {
+    use std::io::Read;
+
+    let is_gzip_magic = data.len() >= 2 && data[0] == 0x1f && data[1] == 0x8b;
+    let is_zlib_magic = data.first() == Some(&0x78);
+
+    let encoding = content_encoding.map(str::to_ascii_lowercase);
+    let wants_gzip = encoding.as_deref() == Some("gzip") || is_gzip_magic;
+    let wants_deflate = encoding.as_deref() == Some("deflate") || (!wants_gzip && is_zlib_magic);
+    let wants_brotli = encoding.as_deref() == Some("br");
+
+    if wants_gzip {
+        let mut decoder = flate2::read::GzDecoder::new(data);
+        let mut out = Vec::new();
+        decoder
+            .read_to_end(&mut out)
+            .context("Failed to decompress response body: not a valid gzip stream")?;
+        Ok(out)
+    } else if wants_deflate {
+        let mut decoder = flate2::read::ZlibDecoder::new(data);
+        let mut out = Vec::new();
+        decoder
+            .read_to_end(&mut out)
+            .context("Failed to decompress response body: not a valid zlib/deflate stream")?;
+        Ok(out)
+    } else if wants_brotli {
+        let mut out = Vec::new();
+        brotli::BrotliDecompress(&mut std::io::Cursor::new(data), &mut out)
+            .context("Failed to decompress response body: not a valid brotli stream")?;
+        Ok(out)
+    } else {
+        Ok(data.to_vec())
+    }
 }
 
 /// ジグザグエンコーディングをデコード
@@ -153,18 +257,41 @@ pub fn decode_zigzag(value: u32) -> i32 {
     ((value >> 1) as i32) ^ (-((value & 1) as i32))
 }
 
+/// ジグザグエンコーディングでエンコード（`decode_zigzag`の逆変換）
+///
+/// ref: https://protobuf.dev/programming-guides/encoding/
+pub fn encode_zigzag(value: i32) -> u32 {
+    ((value << 1) ^ (value >> 31)) as u32
+}
+
+fn encode_command(cmd: u32, count: usize) -> u32 {
+    ((count as u32) << 3) | cmd
+}
+
 pub fn parse_command(cmd_int: u32) -> (u32, usize) {
     let cmd = cmd_int & 0x7;
     let count = (cmd_int >> 3) as usize;
     (cmd, count)
 }
 
-fn decode_geometry(
-    geometry: &[u32],
-    geom_type: vector_tile::tile::GeomType,
-    extent: Extent,
-) -> serde_json::Value {
-    let mut coordinates = Vec::new();
+/// タイル座標系でのリングの符号付き面積（シューレース公式）
+///
+/// MVT仕様では外環（exterior ring）が正、内環（interior ring）が負になる
+pub fn signed_ring_area(ring: &[(i32, i32)]) -> f64 {
+    let mut sum = 0.0;
+    for w in ring.windows(2) {
+        let (x0, y0) = w[0];
+        let (x1, y1) = w[1];
+        sum += (x0 as f64) * (y1 as f64) - (x1 as f64) * (y0 as f64);
+    }
+    sum / 2.0
+}
+
+/// コマンドストリームをMoveTo単位のパート（頂点列）に分解する
+///
+/// `ClosePath`はパートの終端に先頭頂点を追加してリングを閉じる
+fn collect_parts(geometry: &[u32]) -> Vec<Vec<(i32, i32)>> {
+    let mut parts: Vec<Vec<(i32, i32)>> = Vec::new();
     let mut x = 0i32;
     let mut y = 0i32;
     let mut i = 0;
@@ -185,27 +312,7 @@ fn decode_geometry(
                     x += dx;
                     y += dy;
                     i += 2;
-
-                    let norm_x = extent.normalize(x);
-                    let norm_y = extent.normalize(y);
-
-                    match geom_type {
-                        vector_tile::tile::GeomType::Point => {
-                            coordinates.push(serde_json::json!([norm_x, norm_y]));
-                        }
-                        vector_tile::tile::GeomType::Linestring
-                        | vector_tile::tile::GeomType::Polygon => {
-                            if coordinates.is_empty() {
-                                coordinates.push(serde_json::json!([]));
-                            }
-                            if let Some(last) = coordinates.last_mut() {
-                                if let Some(arr) = last.as_array_mut() {
-                                    arr.push(serde_json::json!([norm_x, norm_y]));
-                                }
-                            }
-                        }
-                        _ => {}
-                    }
+                    parts.push(vec![(x, y)]);
                 }
             }
             2 => {
@@ -218,38 +325,119 @@ fn decode_geometry(
                     x += dx;
                     y += dy;
                     i += 2;
-
-                    let norm_x = extent.normalize(x);
-                    let norm_y = extent.normalize(y);
-
-                    if let Some(last) = coordinates.last_mut() {
-                        if let Some(arr) = last.as_array_mut() {
-                            arr.push(serde_json::json!([norm_x, norm_y]));
-                        }
+                    if let Some(last) = parts.last_mut() {
+                        last.push((x, y));
+                    }
+                }
+            }
+            7 => {
+                if let Some(last) = parts.last_mut() {
+                    if let Some(&first) = last.first() {
+                        last.push(first);
                     }
                 }
             }
-            7 => {}
             _ => {}
         }
     }
 
+    parts
+}
+
+/// 外環・内環のグルーピングでポリゴンのパート列をMultiPolygon構造にまとめる
+///
+/// 符号付き面積が正のリングを新しいポリゴンの外環とし、続く負のリングを
+/// その内環として取り込む
+fn group_polygon_rings(parts: Vec<Vec<(i32, i32)>>) -> Vec<Vec<Vec<(i32, i32)>>> {
+    let mut polygons: Vec<Vec<Vec<(i32, i32)>>> = Vec::new();
+
+    for ring in parts {
+        let is_exterior = signed_ring_area(&ring) > 0.0;
+        if is_exterior || polygons.is_empty() {
+            polygons.push(vec![ring]);
+        } else if let Some(last) = polygons.last_mut() {
+            last.push(ring);
+        }
+    }
+
+    polygons
+}
+
+fn decode_geometry(
+    geometry: &[u32],
+    geom_type: vector_tile::tile::GeomType,
+    extent: Extent,
+    reproject: Option<TileCoord>,
+    format: CoordFormat,
+) -> (&'static str, serde_json::Value) {
+    let to_point = |(px, py): (i32, i32)| -> serde_json::Value {
+        let norm_x = extent.normalize(px);
+        let norm_y = extent.normalize(py);
+        let (cx, cy) = match reproject {
+            Some(tile) => tile_to_lonlat(tile, norm_x, norm_y),
+            None => (norm_x, norm_y),
+        };
+        serde_json::json!([format.apply(cx), format.apply(cy)])
+    };
+    let to_ring = |ring: &[(i32, i32)]| -> serde_json::Value {
+        serde_json::json!(ring.iter().copied().map(to_point).collect::<Vec<_>>())
+    };
+
+    let parts = collect_parts(geometry);
+
     match geom_type {
-        vector_tile::tile::GeomType::Point if coordinates.len() == 1 => coordinates[0].clone(),
-        vector_tile::tile::GeomType::Linestring if coordinates.len() == 1 => {
-            coordinates[0].clone()
+        vector_tile::tile::GeomType::Point => {
+            let points: Vec<(i32, i32)> = parts.into_iter().flatten().collect();
+            if points.len() == 1 {
+                ("Point", to_point(points[0]))
+            } else {
+                (
+                    "MultiPoint",
+                    serde_json::json!(points.into_iter().map(to_point).collect::<Vec<_>>()),
+                )
+            }
         }
-        _ => serde_json::json!(coordinates),
+        vector_tile::tile::GeomType::Linestring => {
+            if parts.len() == 1 {
+                ("LineString", to_ring(&parts[0]))
+            } else {
+                (
+                    "MultiLineString",
+                    serde_json::json!(parts.iter().map(|part| to_ring(part)).collect::<Vec<_>>()),
+                )
+            }
+        }
+        vector_tile::tile::GeomType::Polygon => {
+            let polygons = group_polygon_rings(parts);
+            if polygons.len() == 1 {
+                let rings = &polygons[0];
+                (
+                    "Polygon",
+                    serde_json::json!(rings.iter().map(|r| to_ring(r)).collect::<Vec<_>>()),
+                )
+            } else {
+                (
+                    "MultiPolygon",
+                    serde_json::json!(polygons
+                        .iter()
+                        .map(|rings| serde_json::json!(
+                            rings.iter().map(|r| to_ring(r)).collect::<Vec<_>>()
+                        ))
+                        .collect::<Vec<_>>()),
+                )
+            }
+        }
+        _ => ("Unknown", serde_json::Value::Null),
     }
 }
 
-fn convert_value(value: &vector_tile::tile::Value) -> serde_json::Value {
+fn convert_value(value: &vector_tile::tile::Value, format: CoordFormat) -> serde_json::Value {
     if let Some(v) = value.string_value.as_ref() {
         serde_json::Value::String(v.clone())
     } else if let Some(v) = value.float_value {
-        serde_json::json!(v)
+        format.apply(v as f64)
     } else if let Some(v) = value.double_value {
-        serde_json::json!(v)
+        format.apply(v)
     } else if let Some(v) = value.int_value {
         serde_json::json!(v)
     } else if let Some(v) = value.uint_value {
@@ -263,35 +451,38 @@ fn convert_value(value: &vector_tile::tile::Value) -> serde_json::Value {
     }
 }
 
-/// MVTバイナリデータをJSONに変換
+/// MVTバイナリデータをGeoJSONの`FeatureCollection`に変換
+///
+/// `reproject`にタイル座標を渡すと、各座標をタイルローカルの正規化座標ではなく
+/// WGS84（EPSG:4326）の経度緯度に変換して出力する。`format`で出力座標（および
+/// 浮動小数点数のプロパティ値）の丸め桁数とf32narrowingを制御できる。
+/// 整数・真偽値・文字列のプロパティ値には適用されない
 ///
 /// # Arguments
 /// * `data` - MVTのバイナリデータ
+/// * `reproject` - WGS84へ変換する場合のタイル座標（`z`, `x`, `y`）
+/// * `format` - 出力座標の丸め桁数とf32narrowingの設定
 ///
 /// # Errors
 /// Protocol Buffersのデコードに失敗した場合
-pub fn mvt_to_json(data: &[u8]) -> Result<TileData> {
+pub fn mvt_to_json(
+    data: &[u8],
+    reproject: Option<TileCoord>,
+    format: CoordFormat,
+) -> Result<FeatureCollection> {
     let tile = vector_tile::Tile::decode(data).context("Failed to decode MVT protobuf")?;
 
-    let mut layers = Vec::new();
+    let mut features = Vec::new();
 
     for layer in tile.layers {
         let extent = layer.extent.map(Extent::new).unwrap_or_default();
-        let version = layer.version;
-        let mut features = Vec::new();
 
         for feature in layer.features {
             let geom_type = vector_tile::tile::GeomType::try_from(feature.r#type.unwrap_or(0))
                 .unwrap_or(vector_tile::tile::GeomType::Unknown);
 
-            let geometry_type = match geom_type {
-                vector_tile::tile::GeomType::Point => "Point",
-                vector_tile::tile::GeomType::Linestring => "LineString",
-                vector_tile::tile::GeomType::Polygon => "Polygon",
-                _ => "Unknown",
-            };
-
-            let coordinates = decode_geometry(&feature.geometry, geom_type, extent);
+            let (geometry_type, coordinates) =
+                decode_geometry(&feature.geometry, geom_type, extent, reproject, format);
 
             let mut properties = HashMap::new();
             let tags = feature.tags;
@@ -303,7 +494,7 @@ pub fn mvt_to_json(data: &[u8]) -> Result<TileData> {
 
                     if key_idx < layer.keys.len() && val_idx < layer.values.len() {
                         let key = layer.keys[key_idx].clone();
-                        let value = convert_value(&layer.values[val_idx]);
+                        let value = convert_value(&layer.values[val_idx], format);
                         properties.insert(key, value);
                     }
                 }
@@ -317,16 +508,383 @@ pub fn mvt_to_json(data: &[u8]) -> Result<TileData> {
                     coordinates,
                 },
                 properties,
+                layer: layer.name.clone(),
             });
         }
+    }
+
+    Ok(FeatureCollection {
+        type_: "FeatureCollection".to_string(),
+        features,
+    })
+}
 
-        layers.push(Layer {
-            name: layer.name,
-            extent: extent.value(),
-            version,
-            features,
+fn extract_point(value: &serde_json::Value) -> Result<(f64, f64)> {
+    let arr = value
+        .as_array()
+        .context("Expected a [lon, lat] coordinate pair")?;
+    let lon = arr.first().and_then(|v| v.as_f64()).context("Missing longitude")?;
+    let lat = arr.get(1).and_then(|v| v.as_f64()).context("Missing latitude")?;
+    Ok((lon, lat))
+}
+
+fn extract_line(value: &serde_json::Value) -> Result<Vec<(f64, f64)>> {
+    value
+        .as_array()
+        .context("Expected an array of coordinate pairs")?
+        .iter()
+        .map(extract_point)
+        .collect()
+}
+
+fn extract_rings(value: &serde_json::Value) -> Result<Vec<Vec<(f64, f64)>>> {
+    value
+        .as_array()
+        .context("Expected an array of rings/lines")?
+        .iter()
+        .map(extract_line)
+        .collect()
+}
+
+fn build_tile_value(value: &serde_json::Value) -> vector_tile::tile::Value {
+    let mut tv = vector_tile::tile::Value::default();
+    match value {
+        serde_json::Value::String(s) => tv.string_value = Some(s.clone()),
+        serde_json::Value::Bool(b) => tv.bool_value = Some(*b),
+        serde_json::Value::Number(n) => {
+            if let Some(i) = n.as_i64() {
+                tv.int_value = Some(i);
+            } else if let Some(u) = n.as_u64() {
+                tv.uint_value = Some(u);
+            } else if let Some(f) = n.as_f64() {
+                tv.double_value = Some(f);
+            }
+        }
+        _ => tv.string_value = Some(value.to_string()),
+    }
+    tv
+}
+
+/// リングの符号付き面積を目的の向き（外環=正、内環=負）に正規化する
+///
+/// RFC7946のGeoJSONは外環CCW・内環CWを想定するが、`lonlat_to_tile_local`のy軸反転で
+/// タイルローカル座標上の符号が反転するため、`encode_parts`に渡す前にここで補正する
+fn normalize_ring_winding(mut ring: Vec<(i32, i32)>, want_exterior: bool) -> Vec<(i32, i32)> {
+    let is_exterior = signed_ring_area(&ring) > 0.0;
+    if is_exterior != want_exterior {
+        ring.reverse();
+    }
+    ring
+}
+
+/// パート（頂点列）をMVTのコマンド整数列にエンコードする（`decode_geometry`の逆変換）
+///
+/// カーソルはフィーチャ全体を通じて累積され、パート/リングの境界ではリセットしない
+fn encode_parts(parts: &[Vec<(i32, i32)>], geom_type: vector_tile::tile::GeomType) -> Vec<u32> {
+    let mut out = Vec::new();
+    let mut cx = 0i32;
+    let mut cy = 0i32;
+
+    let mut push_point = |out: &mut Vec<u32>, cx: &mut i32, cy: &mut i32, px: i32, py: i32| {
+        out.push(encode_zigzag(px - *cx));
+        out.push(encode_zigzag(py - *cy));
+        *cx = px;
+        *cy = py;
+    };
+
+    match geom_type {
+        vector_tile::tile::GeomType::Point => {
+            let points: Vec<(i32, i32)> = parts.iter().flatten().copied().collect();
+            out.push(encode_command(1, points.len()));
+            for (px, py) in points {
+                push_point(&mut out, &mut cx, &mut cy, px, py);
+            }
+        }
+        vector_tile::tile::GeomType::Linestring => {
+            for part in parts {
+                if part.is_empty() {
+                    continue;
+                }
+                out.push(encode_command(1, 1));
+                push_point(&mut out, &mut cx, &mut cy, part[0].0, part[0].1);
+
+                if part.len() > 1 {
+                    out.push(encode_command(2, part.len() - 1));
+                    for &(px, py) in &part[1..] {
+                        push_point(&mut out, &mut cx, &mut cy, px, py);
+                    }
+                }
+            }
+        }
+        vector_tile::tile::GeomType::Polygon => {
+            for ring in parts {
+                // GeoJSON closes a ring by repeating the first vertex; ClosePath does that for us.
+                let ring: &[(i32, i32)] = if ring.len() > 1 && ring.first() == ring.last() {
+                    &ring[..ring.len() - 1]
+                } else {
+                    ring
+                };
+                if ring.is_empty() {
+                    continue;
+                }
+
+                out.push(encode_command(1, 1));
+                push_point(&mut out, &mut cx, &mut cy, ring[0].0, ring[0].1);
+
+                if ring.len() > 1 {
+                    out.push(encode_command(2, ring.len() - 1));
+                    for &(px, py) in &ring[1..] {
+                        push_point(&mut out, &mut cx, &mut cy, px, py);
+                    }
+                }
+
+                out.push(encode_command(7, 1));
+            }
+        }
+        _ => {}
+    }
+
+    out
+}
+
+struct LayerBuilder {
+    features: Vec<vector_tile::tile::Feature>,
+    keys: Vec<String>,
+    values: Vec<vector_tile::tile::Value>,
+}
+
+/// GeoJSON `FeatureCollection`をMVTのprotobufバイト列にエンコードする（`mvt_to_json`の逆変換）
+///
+/// 各フィーチャーの経度緯度は`lonlat_to_tile_local`で`tile_coord`と`extent`を基準に
+/// タイルローカル座標へ変換してからコマンドストリームを組み立てる
+///
+/// # Errors
+/// フィーチャーのジオメトリが期待するGeoJSON形状でない場合
+pub fn json_to_mvt(
+    tile_data: &FeatureCollection,
+    extent: Extent,
+    tile_coord: TileCoord,
+) -> Result<Vec<u8>> {
+    let mut layer_order: Vec<String> = Vec::new();
+    let mut layers: HashMap<String, LayerBuilder> = HashMap::new();
+
+    for feature in &tile_data.features {
+        let layer_name = feature.layer.clone();
+        if !layers.contains_key(&layer_name) {
+            layer_order.push(layer_name.clone());
+            layers.insert(
+                layer_name.clone(),
+                LayerBuilder {
+                    features: Vec::new(),
+                    keys: Vec::new(),
+                    values: Vec::new(),
+                },
+            );
+        }
+
+        let to_local =
+            |(lon, lat): (f64, f64)| lonlat_to_tile_local(tile_coord, lon, lat, extent);
+
+        let (geom_type, parts): (vector_tile::tile::GeomType, Vec<Vec<(i32, i32)>>) =
+            match feature.geometry.type_.as_str() {
+                "Point" => {
+                    let point = extract_point(&feature.geometry.coordinates)?;
+                    (vector_tile::tile::GeomType::Point, vec![vec![to_local(point)]])
+                }
+                "MultiPoint" => {
+                    let points = extract_line(&feature.geometry.coordinates)?;
+                    let parts = points.into_iter().map(|p| vec![to_local(p)]).collect();
+                    (vector_tile::tile::GeomType::Point, parts)
+                }
+                "LineString" => {
+                    let line = extract_line(&feature.geometry.coordinates)?;
+                    let local = line.into_iter().map(to_local).collect();
+                    (vector_tile::tile::GeomType::Linestring, vec![local])
+                }
+                "MultiLineString" => {
+                    let lines = extract_rings(&feature.geometry.coordinates)?;
+                    let parts = lines
+                        .into_iter()
+                        .map(|line| line.into_iter().map(to_local).collect())
+                        .collect();
+                    (vector_tile::tile::GeomType::Linestring, parts)
+                }
+                "Polygon" => {
+                    let rings = extract_rings(&feature.geometry.coordinates)?;
+                    let parts = rings
+                        .into_iter()
+                        .enumerate()
+                        .map(|(i, ring)| {
+                            let local: Vec<(i32, i32)> = ring.into_iter().map(to_local).collect();
+                            normalize_ring_winding(local, i == 0)
+                        })
+                        .collect();
+                    (vector_tile::tile::GeomType::Polygon, parts)
+                }
+                "MultiPolygon" => {
+                    let mut parts = Vec::new();
+                    for polygon in feature
+                        .geometry
+                        .coordinates
+                        .as_array()
+                        .context("Expected an array of polygons")?
+                    {
+                        for (i, ring) in extract_rings(polygon)?.into_iter().enumerate() {
+                            let local: Vec<(i32, i32)> = ring.into_iter().map(to_local).collect();
+                            parts.push(normalize_ring_winding(local, i == 0));
+                        }
+                    }
+                    (vector_tile::tile::GeomType::Polygon, parts)
+                }
+                other => anyhow::bail!("Unsupported GeoJSON geometry type: {other}"),
+            };
+
+        let geometry = encode_parts(&parts, geom_type);
+        let builder = layers.get_mut(&layer_name).expect("layer was just inserted");
+
+        let mut tags = Vec::new();
+        for (key, value) in &feature.properties {
+            let key_idx = match builder.keys.iter().position(|k| k == key) {
+                Some(idx) => idx,
+                None => {
+                    builder.keys.push(key.clone());
+                    builder.keys.len() - 1
+                }
+            };
+            let tile_value = build_tile_value(value);
+            let val_idx = match builder.values.iter().position(|v| v == &tile_value) {
+                Some(idx) => idx,
+                None => {
+                    builder.values.push(tile_value);
+                    builder.values.len() - 1
+                }
+            };
+            tags.push(key_idx as u32);
+            tags.push(val_idx as u32);
+        }
+
+        builder.features.push(vector_tile::tile::Feature {
+            id: feature.id,
+            tags,
+            r#type: Some(geom_type as i32),
+            geometry,
         });
     }
 
-    Ok(TileData { layers })
+    let layers: Vec<vector_tile::tile::Layer> = layer_order
+        .into_iter()
+        .map(|name| {
+            let builder = layers.remove(&name).expect("layer present in map");
+            vector_tile::tile::Layer {
+                version: 2,
+                name,
+                features: builder.features,
+                keys: builder.keys,
+                values: builder.values,
+                extent: Some(extent.value()),
+            }
+        })
+        .collect();
+
+    Ok(vector_tile::Tile { layers }.encode_to_vec())
+}
+
+fn geojson_coord(value: &serde_json::Value) -> Option<(f64, f64)> {
+    let arr = value.as_array()?;
+    Some((arr.first()?.as_f64()?, arr.get(1)?.as_f64()?))
+}
+
+fn geojson_line(value: &serde_json::Value) -> Option<Vec<(f64, f64)>> {
+    value.as_array()?.iter().map(geojson_coord).collect()
+}
+
+fn geojson_rings(value: &serde_json::Value) -> Option<Vec<Vec<(f64, f64)>>> {
+    value.as_array()?.iter().map(geojson_line).collect()
+}
+
+fn geojson_polygon(value: &serde_json::Value) -> Option<Polygon<f64>> {
+    let mut rings = geojson_rings(value)?.into_iter();
+    let exterior = LineString::from(rings.next()?);
+    let interiors = rings.map(LineString::from).collect();
+    Some(Polygon::new(exterior, interiors))
+}
+
+/// GeoJSONジオメトリを`geo`クレートの`Geometry`に変換する（交差・内包判定用）
+fn geojson_to_geo(geometry: &GeoJsonGeometry) -> Option<Geometry<f64>> {
+    match geometry.type_.as_str() {
+        "Point" => {
+            let (x, y) = geojson_coord(&geometry.coordinates)?;
+            Some(Geometry::Point(GeoPoint::new(x, y)))
+        }
+        "MultiPoint" => {
+            let points = geojson_line(&geometry.coordinates)?
+                .into_iter()
+                .map(|(x, y)| GeoPoint::new(x, y))
+                .collect();
+            Some(Geometry::MultiPoint(MultiPoint(points)))
+        }
+        "LineString" => {
+            let points = geojson_line(&geometry.coordinates)?;
+            Some(Geometry::LineString(LineString::from(points)))
+        }
+        "MultiLineString" => {
+            let lines = geojson_rings(&geometry.coordinates)?
+                .into_iter()
+                .map(LineString::from)
+                .collect();
+            Some(Geometry::MultiLineString(MultiLineString(lines)))
+        }
+        "Polygon" => Some(Geometry::Polygon(geojson_polygon(&geometry.coordinates)?)),
+        "MultiPolygon" => {
+            let polygons = geometry
+                .coordinates
+                .as_array()?
+                .iter()
+                .map(geojson_polygon)
+                .collect::<Option<Vec<_>>>()?;
+            Some(Geometry::MultiPolygon(MultiPolygon(polygons)))
+        }
+        _ => None,
+    }
+}
+
+/// レイヤー名・バウンディングボックス・内包点でフィーチャーを絞り込む
+///
+/// `bbox`は`(min_lon, min_lat, max_lon, max_lat)`、`contains`は`(lon, lat)`。
+/// どちらも与えられた場合は両方の条件を満たすフィーチャーのみ残す。
+/// `mvt_to_json`でのデコード（および`--geographic`による再投影）の後に適用する想定
+pub fn filter_features(
+    collection: FeatureCollection,
+    layer: Option<&str>,
+    bbox: Option<(f64, f64, f64, f64)>,
+    contains: Option<(f64, f64)>,
+) -> FeatureCollection {
+    let features = collection
+        .features
+        .into_iter()
+        .filter(|feature| layer.map(|name| feature.layer == name).unwrap_or(true))
+        .filter(|feature| {
+            let Some(geom) = geojson_to_geo(&feature.geometry) else {
+                return true;
+            };
+
+            let bbox_ok = bbox
+                .map(|(min_lon, min_lat, max_lon, max_lat)| {
+                    let rect = Rect::new((min_lon, min_lat), (max_lon, max_lat));
+                    geom.intersects(&rect)
+                })
+                .unwrap_or(true);
+
+            let contains_ok = contains
+                .map(|(lon, lat)| geom.contains(&GeoPoint::new(lon, lat)))
+                .unwrap_or(true);
+
+            bbox_ok && contains_ok
+        })
+        .collect();
+
+    FeatureCollection {
+        type_: collection.type_,
+        features,
+    }
 }
@@ -1,19 +1,18 @@
 use anyhow::{Context, Result};
 use clap::Parser;
-use prost::Message;
-use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
-
-mod vector_tile {
-    include!(concat!(env!("OUT_DIR"), "/vector_tile.rs"));
-}
+use mvtcurl::{
+    decode_tile_bytes, fetch_mvt, filter_features, json_to_mvt, mvt_to_json, CoordFormat, Extent,
+    FeatureCollection, PredefinedLocation, TileCoord, DEFAULT_EXTENT,
+};
 
 #[derive(Parser)]
 #[command(name = "mvtcurl")]
 #[command(about = "Fetch MVT (Mapbox Vector Tile) and convert to JSON", long_about = None)]
 struct Cli {
-    #[arg(help = "URL of the MVT tile to fetch (supports {z}/{x}/{y} placeholders)")]
-    url: String,
+    #[arg(
+        help = "URL of the MVT tile to fetch (supports {z}/{x}/{y} placeholders); not needed with --encode"
+    )]
+    url: Option<String>,
 
     #[arg(short, long, help = "Output compact JSON instead of pretty-printed")]
     compact: bool,
@@ -35,243 +34,81 @@ struct Cli {
 
     #[arg(short = 'H', long = "header", help = "Add custom HTTP header (format: 'Name: Value')")]
     headers: Vec<String>,
-}
 
-#[derive(Debug, Serialize, Deserialize)]
-struct GeoJsonFeature {
-    #[serde(rename = "type")]
-    type_: String,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    id: Option<u64>,
-    geometry: GeoJsonGeometry,
-    properties: HashMap<String, serde_json::Value>,
-}
+    #[arg(
+        long = "geographic",
+        visible_alias = "wgs84",
+        help = "Reproject coordinates to WGS84 lon/lat instead of tile-local 0..1"
+    )]
+    geographic: bool,
 
-#[derive(Debug, Serialize, Deserialize)]
-struct GeoJsonGeometry {
-    #[serde(rename = "type")]
-    type_: String,
-    coordinates: serde_json::Value,
-}
+    #[arg(
+        long = "encode",
+        help = "Read a GeoJSON FeatureCollection from stdin and write an encoded .mvt tile to stdout (requires --zoom/--x/--y)"
+    )]
+    encode: bool,
 
-#[derive(Debug, Serialize, Deserialize)]
-struct Layer {
-    name: String,
+    #[arg(long, help = "Tile extent used when encoding with --encode", default_value_t = DEFAULT_EXTENT)]
     extent: u32,
-    version: u32,
-    features: Vec<GeoJsonFeature>,
-}
 
-#[derive(Debug, Serialize, Deserialize)]
-struct TileData {
-    layers: Vec<Layer>,
-}
+    #[arg(
+        long = "no-decompress",
+        help = "Disable automatic gzip/deflate/brotli decompression of the tile response"
+    )]
+    no_decompress: bool,
 
-fn fetch_mvt(url: &str, headers: &[String]) -> Result<Vec<u8>> {
-    let client = reqwest::blocking::Client::new();
-    let mut request = client.get(url);
-
-    for header in headers {
-        let parts: Vec<&str> = header.splitn(2, ':').collect();
-        if parts.len() == 2 {
-            let name = parts[0].trim();
-            let value = parts[1].trim();
-            request = request.header(name, value);
-        } else {
-            anyhow::bail!("Invalid header format: '{}'. Expected 'Name: Value'", header);
-        }
-    }
+    #[arg(long, help = "Only output features from this layer")]
+    layer: Option<String>,
 
-    let response = request
-        .send()
-        .context("Failed to fetch URL")?
-        .bytes()
-        .context("Failed to read response body")?;
-    Ok(response.to_vec())
-}
+    #[arg(
+        long,
+        help = "Only output features intersecting 'minLon,minLat,maxLon,maxLat' (implies --geographic)"
+    )]
+    bbox: Option<String>,
 
-fn decode_geometry(
-    geometry: &[u32],
-    geom_type: vector_tile::tile::GeomType,
-    extent: u32,
-) -> serde_json::Value {
-    let mut coordinates = Vec::new();
-    let mut x = 0i32;
-    let mut y = 0i32;
-    let mut i = 0;
-
-    let extent_f64 = extent as f64;
-
-    while i < geometry.len() {
-        let cmd_int = geometry[i];
-        let cmd = cmd_int & 0x7;
-        let count = (cmd_int >> 3) as usize;
-        i += 1;
-
-        match cmd {
-            1 => {
-                for _ in 0..count {
-                    if i + 1 >= geometry.len() {
-                        break;
-                    }
-                    let dx = ((geometry[i] >> 1) as i32) ^ (-((geometry[i] & 1) as i32));
-                    let dy = ((geometry[i + 1] >> 1) as i32) ^ (-((geometry[i + 1] & 1) as i32));
-                    x += dx;
-                    y += dy;
-                    i += 2;
-
-                    let norm_x = (x as f64) / extent_f64;
-                    let norm_y = (y as f64) / extent_f64;
-
-                    match geom_type {
-                        vector_tile::tile::GeomType::Point => {
-                            coordinates.push(serde_json::json!([norm_x, norm_y]));
-                        }
-                        vector_tile::tile::GeomType::Linestring
-                        | vector_tile::tile::GeomType::Polygon => {
-                            if coordinates.is_empty() {
-                                coordinates.push(serde_json::json!([]));
-                            }
-                            if let Some(last) = coordinates.last_mut() {
-                                if let Some(arr) = last.as_array_mut() {
-                                    arr.push(serde_json::json!([norm_x, norm_y]));
-                                }
-                            }
-                        }
-                        _ => {}
-                    }
-                }
-            }
-            2 => {
-                for _ in 0..count {
-                    if i + 1 >= geometry.len() {
-                        break;
-                    }
-                    let dx = ((geometry[i] >> 1) as i32) ^ (-((geometry[i] & 1) as i32));
-                    let dy = ((geometry[i + 1] >> 1) as i32) ^ (-((geometry[i + 1] & 1) as i32));
-                    x += dx;
-                    y += dy;
-                    i += 2;
-
-                    let norm_x = (x as f64) / extent_f64;
-                    let norm_y = (y as f64) / extent_f64;
-
-                    if let Some(last) = coordinates.last_mut() {
-                        if let Some(arr) = last.as_array_mut() {
-                            arr.push(serde_json::json!([norm_x, norm_y]));
-                        }
-                    }
-                }
-            }
-            7 => {}
-            _ => {}
-        }
-    }
+    #[arg(
+        long,
+        help = "Only output features containing the point 'lon,lat' (implies --geographic)"
+    )]
+    contains: Option<String>,
 
-    match geom_type {
-        vector_tile::tile::GeomType::Point if coordinates.len() == 1 => coordinates[0].clone(),
-        vector_tile::tile::GeomType::Linestring if coordinates.len() == 1 => {
-            coordinates[0].clone()
-        }
-        _ => serde_json::json!(coordinates),
-    }
-}
+    #[arg(long, help = "Round emitted coordinates to this many decimal places")]
+    precision: Option<u32>,
 
-fn convert_value(value: &vector_tile::tile::Value) -> serde_json::Value {
-    if let Some(v) = value.string_value.as_ref() {
-        serde_json::Value::String(v.clone())
-    } else if let Some(v) = value.float_value {
-        serde_json::json!(v)
-    } else if let Some(v) = value.double_value {
-        serde_json::json!(v)
-    } else if let Some(v) = value.int_value {
-        serde_json::json!(v)
-    } else if let Some(v) = value.uint_value {
-        serde_json::json!(v)
-    } else if let Some(v) = value.sint_value {
-        serde_json::json!(v)
-    } else if let Some(v) = value.bool_value {
-        serde_json::Value::Bool(v)
-    } else {
-        serde_json::Value::Null
-    }
+    #[arg(long = "float32", help = "Narrow emitted coordinates to f32 instead of f64")]
+    float32: bool,
 }
 
-fn mvt_to_json(data: &[u8]) -> Result<TileData> {
-    let tile = vector_tile::Tile::decode(data).context("Failed to decode MVT protobuf")?;
-
-    let mut layers = Vec::new();
-
-    for layer in tile.layers {
-        let extent = layer.extent.unwrap_or(4096);
-        let version = layer.version;
-        let mut features = Vec::new();
-
-        for feature in layer.features {
-            let geom_type = vector_tile::tile::GeomType::try_from(feature.r#type.unwrap_or(0))
-                .unwrap_or(vector_tile::tile::GeomType::Unknown);
-
-            let geometry_type = match geom_type {
-                vector_tile::tile::GeomType::Point => "Point",
-                vector_tile::tile::GeomType::Linestring => "LineString",
-                vector_tile::tile::GeomType::Polygon => "Polygon",
-                _ => "Unknown",
-            };
-
-            let coordinates = decode_geometry(&feature.geometry, geom_type, extent);
-
-            let mut properties = HashMap::new();
-            let tags = feature.tags;
-
-            for i in (0..tags.len()).step_by(2) {
-                if i + 1 < tags.len() {
-                    let key_idx = tags[i] as usize;
-                    let val_idx = tags[i + 1] as usize;
-
-                    if key_idx < layer.keys.len() && val_idx < layer.values.len() {
-                        let key = layer.keys[key_idx].clone();
-                        let value = convert_value(&layer.values[val_idx]);
-                        properties.insert(key, value);
-                    }
-                }
-            }
-
-            features.push(GeoJsonFeature {
-                type_: "Feature".to_string(),
-                id: feature.id,
-                geometry: GeoJsonGeometry {
-                    type_: geometry_type.to_string(),
-                    coordinates,
-                },
-                properties,
-            });
-        }
-
-        layers.push(Layer {
-            name: layer.name,
-            extent,
-            version,
-            features,
-        });
-    }
-
-    Ok(TileData { layers })
+fn parse_bbox(s: &str) -> Result<(f64, f64, f64, f64)> {
+    let parts: Vec<&str> = s.split(',').collect();
+    anyhow::ensure!(
+        parts.len() == 4,
+        "Invalid --bbox '{s}'. Expected 'minLon,minLat,maxLon,maxLat'"
+    );
+    let values: Vec<f64> = parts
+        .iter()
+        .map(|p| p.trim().parse::<f64>())
+        .collect::<std::result::Result<_, _>>()
+        .with_context(|| format!("Invalid --bbox '{s}'. Expected four comma-separated numbers"))?;
+    Ok((values[0], values[1], values[2], values[3]))
 }
 
-fn lat_lon_to_tile(lat: f64, lon: f64, zoom: u32) -> (u32, u32) {
-    let n = 2_f64.powi(zoom as i32);
-    let x = ((lon + 180.0) / 360.0 * n).floor() as u32;
-    let lat_rad = lat.to_radians();
-    let y = ((1.0 - (lat_rad.tan() + (1.0 / lat_rad.cos())).ln() / std::f64::consts::PI) / 2.0 * n)
-        .floor() as u32;
-    (x, y)
+fn parse_point(s: &str) -> Result<(f64, f64)> {
+    let parts: Vec<&str> = s.split(',').collect();
+    anyhow::ensure!(parts.len() == 2, "Invalid point '{s}'. Expected 'lon,lat'");
+    let values: Vec<f64> = parts
+        .iter()
+        .map(|p| p.trim().parse::<f64>())
+        .collect::<std::result::Result<_, _>>()
+        .with_context(|| format!("Invalid point '{s}'. Expected two comma-separated numbers"))?;
+    Ok((values[0], values[1]))
 }
 
-fn build_url(cli: &Cli) -> Result<String> {
-    let mut url = cli.url.clone();
+fn build_url(cli: &Cli) -> Result<(String, Option<TileCoord>)> {
+    let mut url = cli.url.clone().context("URL is required")?;
 
     if !url.contains("{z}") && !url.contains("{x}") && !url.contains("{y}") {
-        return Ok(url);
+        return Ok((url, None));
     }
 
     let zoom = if cli.tokyo || cli.fuji {
@@ -281,9 +118,11 @@ fn build_url(cli: &Cli) -> Result<String> {
     };
 
     let (x, y) = if cli.tokyo {
-        lat_lon_to_tile(35.681236, 139.767125, zoom) // Tokyo Station
+        let tile = PredefinedLocation::TokyoStation.coordinates().to_tile_coord(zoom);
+        (tile.x, tile.y)
     } else if cli.fuji {
-        lat_lon_to_tile(35.360556, 138.727778, zoom) // Mt. Fuji summit
+        let tile = PredefinedLocation::MtFuji.coordinates().to_tile_coord(zoom);
+        (tile.x, tile.y)
     } else {
         let x = cli.x.unwrap_or(0);
         let y = cli.y.unwrap_or(0);
@@ -294,15 +133,61 @@ fn build_url(cli: &Cli) -> Result<String> {
     url = url.replace("{x}", &x.to_string());
     url = url.replace("{y}", &y.to_string());
 
-    Ok(url)
+    Ok((url, Some(TileCoord::new(zoom, x, y))))
 }
 
 fn main() -> Result<()> {
+    use std::io::{Read, Write};
+
     let cli = Cli::parse();
 
-    let url = build_url(&cli)?;
-    let data = fetch_mvt(&url, &cli.headers)?;
-    let tile_data = mvt_to_json(&data)?;
+    if cli.encode {
+        let mut input = String::new();
+        std::io::stdin()
+            .read_to_string(&mut input)
+            .context("Failed to read GeoJSON from stdin")?;
+        let collection: FeatureCollection =
+            serde_json::from_str(&input).context("Failed to parse GeoJSON FeatureCollection")?;
+
+        let tile_coord = TileCoord::new(
+            cli.zoom.context("--zoom is required with --encode")?,
+            cli.x.context("--x is required with --encode")?,
+            cli.y.context("--y is required with --encode")?,
+        );
+
+        let bytes = json_to_mvt(&collection, Extent::new(cli.extent), tile_coord)?;
+        std::io::stdout()
+            .write_all(&bytes)
+            .context("Failed to write encoded MVT to stdout")?;
+        return Ok(());
+    }
+
+    let bbox = cli.bbox.as_deref().map(parse_bbox).transpose()?;
+    let contains = cli.contains.as_deref().map(parse_point).transpose()?;
+
+    // --bbox/--contains are documented in lon/lat, so they only make sense against
+    // reprojected output; treat them as an implicit --geographic rather than
+    // silently filtering tile-local 0..1 coordinates against a degrees box.
+    let geographic = cli.geographic || bbox.is_some() || contains.is_some();
+
+    let (url, tile_coord) = build_url(&cli)?;
+    let reproject = if geographic {
+        Some(tile_coord.context(
+            "--geographic (implied by --bbox/--contains) requires {z}/{x}/{y} placeholders to resolve a tile coordinate",
+        )?)
+    } else {
+        None
+    };
+    let (data, content_encoding) = fetch_mvt(&url, &cli.headers)?;
+    let data = if cli.no_decompress {
+        data
+    } else {
+        decode_tile_bytes(&data, content_encoding.as_deref())?
+    };
+    let format = CoordFormat::new(cli.precision, cli.float32);
+    let tile_data = mvt_to_json(&data, reproject, format)?;
+
+    let tile_data = filter_features(tile_data, cli.layer.as_deref(), bbox, contains);
 
     let output = if cli.compact {
         serde_json::to_string(&tile_data)?
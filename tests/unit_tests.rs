@@ -80,3 +80,370 @@ fn test_lat_lon_new() {
     assert_eq!(latlon.lat, 35.6812);
     assert_eq!(latlon.lon, 139.7671);
 }
+
+#[test]
+fn test_tile_to_lonlat_origin() {
+    let tile = TileCoord::new(0, 0, 0);
+    let (lon, lat) = tile_to_lonlat(tile, 0.0, 0.0);
+    assert_eq!(lon, -180.0);
+    assert!((lat - 85.05112877980659).abs() < 1e-9);
+}
+
+#[test]
+fn test_tile_to_lonlat_center() {
+    let tile = TileCoord::new(0, 0, 0);
+    let (lon, lat) = tile_to_lonlat(tile, 0.5, 0.5);
+    assert!(lon.abs() < 1e-9);
+    assert!(lat.abs() < 1e-9);
+}
+
+#[test]
+fn test_tile_to_lonlat_matches_lat_lon_to_tile_roundtrip() {
+    let latlon = LatLon::new(TOKYO_STATION_LAT, TOKYO_STATION_LON);
+    let tile = latlon.to_tile_coord(14);
+    let (lon, lat) = tile_to_lonlat(tile, 0.0, 0.0);
+    assert!((lon - TOKYO_STATION_LON).abs() < 0.1);
+    assert!((lat - TOKYO_STATION_LAT).abs() < 0.1);
+}
+
+#[test]
+fn test_signed_ring_area_ccw_is_positive() {
+    // square traversed counter-clockwise, closed
+    let ring = vec![(0, 0), (10, 0), (10, 10), (0, 10), (0, 0)];
+    assert_eq!(signed_ring_area(&ring), 100.0);
+}
+
+#[test]
+fn test_signed_ring_area_cw_is_negative() {
+    // same square traversed clockwise (interior ring convention)
+    let ring = vec![(0, 0), (0, 10), (10, 10), (10, 0), (0, 0)];
+    assert_eq!(signed_ring_area(&ring), -100.0);
+}
+
+#[test]
+fn test_encode_zigzag_round_trips_decode_zigzag() {
+    for value in [0, -1, 1, -2, 2, 1000, -1000] {
+        assert_eq!(decode_zigzag(encode_zigzag(value)), value);
+    }
+}
+
+#[test]
+fn test_encode_zigzag_matches_protobuf_table() {
+    assert_eq!(encode_zigzag(0), 0);
+    assert_eq!(encode_zigzag(-1), 1);
+    assert_eq!(encode_zigzag(1), 2);
+    assert_eq!(encode_zigzag(-2), 3);
+}
+
+#[test]
+fn test_lonlat_to_tile_local_round_trips_tile_to_lonlat() {
+    let tile = TileCoord::new(14, 14552, 6451);
+    let extent = Extent::new(4096);
+    let (lon, lat) = tile_to_lonlat(tile, 0.25, 0.75);
+    let (x, y) = lonlat_to_tile_local(tile, lon, lat, extent);
+    assert_eq!(x, 1024);
+    assert_eq!(y, 3072);
+}
+
+#[test]
+fn test_decode_tile_bytes_passthrough_uncompressed() {
+    let data = b"not compressed mvt protobuf bytes".to_vec();
+    let decoded = decode_tile_bytes(&data, None).unwrap();
+    assert_eq!(decoded, data);
+}
+
+#[test]
+fn test_decode_tile_bytes_gzip_by_magic_bytes() {
+    use std::io::Write;
+
+    let original = b"hello mvt tile";
+    let mut encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+    encoder.write_all(original).unwrap();
+    let compressed = encoder.finish().unwrap();
+
+    // No Content-Encoding header supplied; detection must rely on the gzip magic bytes.
+    let decoded = decode_tile_bytes(&compressed, None).unwrap();
+    assert_eq!(decoded, original);
+}
+
+#[test]
+fn test_decode_tile_bytes_gzip_by_content_encoding_header() {
+    use std::io::Write;
+
+    let original = b"hello mvt tile";
+    let mut encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+    encoder.write_all(original).unwrap();
+    let compressed = encoder.finish().unwrap();
+
+    let decoded = decode_tile_bytes(&compressed, Some("gzip")).unwrap();
+    assert_eq!(decoded, original);
+}
+
+#[test]
+fn test_decode_tile_bytes_rejects_invalid_gzip_stream() {
+    let fake_gzip = vec![0x1f, 0x8b, 0x00, 0x00];
+    assert!(decode_tile_bytes(&fake_gzip, None).is_err());
+}
+
+fn point_feature(layer: &str, lon: f64, lat: f64) -> GeoJsonFeature {
+    GeoJsonFeature {
+        type_: "Feature".to_string(),
+        id: None,
+        geometry: GeoJsonGeometry {
+            type_: "Point".to_string(),
+            coordinates: serde_json::json!([lon, lat]),
+        },
+        properties: std::collections::HashMap::new(),
+        layer: layer.to_string(),
+    }
+}
+
+#[test]
+fn test_filter_features_by_layer() {
+    let collection = FeatureCollection {
+        type_: "FeatureCollection".to_string(),
+        features: vec![point_feature("roads", 0.0, 0.0), point_feature("water", 1.0, 1.0)],
+    };
+
+    let filtered = filter_features(collection, Some("roads"), None, None);
+    assert_eq!(filtered.features.len(), 1);
+    assert_eq!(filtered.features[0].layer, "roads");
+}
+
+#[test]
+fn test_filter_features_by_bbox() {
+    let collection = FeatureCollection {
+        type_: "FeatureCollection".to_string(),
+        features: vec![point_feature("poi", 5.0, 5.0), point_feature("poi", 50.0, 50.0)],
+    };
+
+    let filtered = filter_features(collection, None, Some((0.0, 0.0, 10.0, 10.0)), None);
+    assert_eq!(filtered.features.len(), 1);
+}
+
+#[test]
+fn test_filter_features_by_contains_point() {
+    let collection = FeatureCollection {
+        type_: "FeatureCollection".to_string(),
+        features: vec![point_feature("poi", 1.0, 1.0)],
+    };
+
+    let matches = filter_features(collection.clone(), None, None, Some((1.0, 1.0)));
+    assert_eq!(matches.features.len(), 1);
+
+    let no_match = filter_features(collection, None, None, Some((2.0, 2.0)));
+    assert_eq!(no_match.features.len(), 0);
+}
+
+#[test]
+fn test_coord_format_default_is_unchanged() {
+    let format = CoordFormat::default();
+    assert_eq!(format.apply(1.0 / 3.0), serde_json::json!(1.0 / 3.0));
+}
+
+#[test]
+fn test_coord_format_precision_rounds() {
+    let format = CoordFormat::new(Some(2), false);
+    assert_eq!(format.apply(1.0 / 3.0), serde_json::json!(0.33));
+}
+
+#[test]
+fn test_coord_format_float32_narrows() {
+    let format = CoordFormat::new(None, true);
+    let value = 1.0 / 3.0_f64;
+    assert_eq!(format.apply(value), serde_json::json!(value as f32));
+}
+
+// --- hand-built MVT protobuf helpers (mirrors the wire format in vector_tile.proto) ---
+
+fn write_varint(mut n: u64, out: &mut Vec<u8>) {
+    loop {
+        let mut byte = (n & 0x7f) as u8;
+        n >>= 7;
+        if n != 0 {
+            byte |= 0x80;
+        }
+        out.push(byte);
+        if n == 0 {
+            break;
+        }
+    }
+}
+
+fn write_tag(field: u32, wire_type: u32, out: &mut Vec<u8>) {
+    write_varint(((field as u64) << 3) | wire_type as u64, out);
+}
+
+fn write_varint_field(field: u32, value: u64, out: &mut Vec<u8>) {
+    write_tag(field, 0, out);
+    write_varint(value, out);
+}
+
+fn write_len_delim(field: u32, bytes: &[u8], out: &mut Vec<u8>) {
+    write_tag(field, 2, out);
+    write_varint(bytes.len() as u64, out);
+    out.extend_from_slice(bytes);
+}
+
+fn write_packed_varints(field: u32, values: &[u32], out: &mut Vec<u8>) {
+    let mut buf = Vec::new();
+    for &v in values {
+        write_varint(v as u64, &mut buf);
+    }
+    write_len_delim(field, &buf, out);
+}
+
+fn build_feature(id: u64, geom_type: u32, geometry: &[u32]) -> Vec<u8> {
+    let mut out = Vec::new();
+    write_varint_field(1, id, &mut out);
+    write_varint_field(3, geom_type as u64, &mut out);
+    write_packed_varints(4, geometry, &mut out);
+    out
+}
+
+fn build_layer(name: &str, extent: u32, features: &[Vec<u8>]) -> Vec<u8> {
+    let mut out = Vec::new();
+    write_len_delim(1, name.as_bytes(), &mut out);
+    for feature in features {
+        write_len_delim(2, feature, &mut out);
+    }
+    write_varint_field(5, extent as u64, &mut out);
+    write_varint_field(15, 2, &mut out);
+    out
+}
+
+fn build_tile(layers: &[Vec<u8>]) -> Vec<u8> {
+    let mut out = Vec::new();
+    for layer in layers {
+        write_len_delim(3, layer, &mut out);
+    }
+    out
+}
+
+#[test]
+fn test_mvt_to_json_decodes_polygon_with_hole_and_multipolygon() {
+    const POLYGON: u32 = 3;
+
+    // Feature 1: a 10x10 exterior ring with a 2x2 interior hole.
+    let polygon_with_hole = vec![
+        9, 0, 0, // MoveTo (0, 0)
+        26, 20, 0, 0, 20, 19, 0, // LineTo (10,0) (10,10) (0,10)
+        15, // ClosePath
+        9, 4, 15, // MoveTo (2, 2), relative to (0, 10)
+        26, 0, 4, 4, 0, 0, 3, // LineTo (2,4) (4,4) (4,2)
+        15, // ClosePath
+    ];
+
+    // Feature 2: two disjoint 4x4 squares -> MultiPolygon.
+    let two_squares = vec![
+        9, 0, 0, // MoveTo (0, 0)
+        26, 8, 0, 0, 8, 7, 0, // LineTo (4,0) (4,4) (0,4)
+        15, // ClosePath
+        9, 20, 12, // MoveTo (10, 10), relative to (0, 4)
+        26, 8, 0, 0, 8, 7, 0, // LineTo (14,10) (14,14) (10,14)
+        15, // ClosePath
+    ];
+
+    let feature1 = build_feature(1, POLYGON, &polygon_with_hole);
+    let feature2 = build_feature(2, POLYGON, &two_squares);
+    let layer = build_layer("test", 16, &[feature1, feature2]);
+    let tile = build_tile(&[layer]);
+
+    let collection = mvt_to_json(&tile, None, CoordFormat::default()).unwrap();
+    assert_eq!(collection.features.len(), 2);
+
+    let polygon = &collection.features[0];
+    assert_eq!(polygon.geometry.type_, "Polygon");
+    let rings = polygon.geometry.coordinates.as_array().unwrap();
+    assert_eq!(rings.len(), 2, "exterior ring plus one hole");
+    let exterior = rings[0].as_array().unwrap();
+    assert_eq!(exterior[0], serde_json::json!([0.0, 0.0]));
+    let hole = rings[1].as_array().unwrap();
+    assert_eq!(hole[0], serde_json::json!([0.125, 0.125]));
+
+    let multi = &collection.features[1];
+    assert_eq!(multi.geometry.type_, "MultiPolygon");
+    let polygons = multi.geometry.coordinates.as_array().unwrap();
+    assert_eq!(polygons.len(), 2, "two disjoint squares stay separate polygons");
+    let first_ring = polygons[0].as_array().unwrap()[0].as_array().unwrap();
+    assert_eq!(first_ring[0], serde_json::json!([0.0, 0.0]));
+    let second_ring = polygons[1].as_array().unwrap()[0].as_array().unwrap();
+    assert_eq!(second_ring[0], serde_json::json!([0.625, 0.625]));
+}
+
+#[test]
+fn test_json_to_mvt_round_trips_through_mvt_to_json() {
+    let mut properties = std::collections::HashMap::new();
+    properties.insert("name".to_string(), serde_json::json!("Tokyo Station"));
+
+    let original = FeatureCollection {
+        type_: "FeatureCollection".to_string(),
+        features: vec![GeoJsonFeature {
+            type_: "Feature".to_string(),
+            id: Some(1),
+            geometry: GeoJsonGeometry {
+                type_: "Point".to_string(),
+                coordinates: serde_json::json!([TOKYO_STATION_LON, TOKYO_STATION_LAT]),
+            },
+            properties: properties.clone(),
+            layer: "poi".to_string(),
+        }],
+    };
+
+    let tile_coord = LatLon::new(TOKYO_STATION_LAT, TOKYO_STATION_LON).to_tile_coord(14);
+    let extent = Extent::default();
+    let encoded = json_to_mvt(&original, extent, tile_coord).unwrap();
+    let decoded = mvt_to_json(&encoded, Some(tile_coord), CoordFormat::default()).unwrap();
+
+    assert_eq!(decoded.features.len(), 1);
+    let feature = &decoded.features[0];
+    assert_eq!(feature.layer, "poi");
+    assert_eq!(feature.geometry.type_, "Point");
+    assert_eq!(feature.properties.get("name"), properties.get("name"));
+
+    let coords = feature.geometry.coordinates.as_array().unwrap();
+    let lon = coords[0].as_f64().unwrap();
+    let lat = coords[1].as_f64().unwrap();
+    assert!((lon - TOKYO_STATION_LON).abs() < 1e-3);
+    assert!((lat - TOKYO_STATION_LAT).abs() < 1e-3);
+}
+
+#[test]
+fn test_json_to_mvt_round_trips_polygon_with_hole_winding() {
+    let lon0 = TOKYO_STATION_LON;
+    let lat0 = TOKYO_STATION_LAT;
+    let d = 0.001; // ~100m square, large enough to survive tile-local rounding
+    let a = lon0 + d * 0.25;
+    let b = lon0 + d * 0.75;
+    let c = lat0 + d * 0.25;
+    let e = lat0 + d * 0.75;
+
+    let original = FeatureCollection {
+        type_: "FeatureCollection".to_string(),
+        features: vec![GeoJsonFeature {
+            type_: "Feature".to_string(),
+            id: None,
+            geometry: GeoJsonGeometry {
+                type_: "Polygon".to_string(),
+                // RFC7946 winding: exterior CCW, hole CW.
+                coordinates: serde_json::json!([
+                    [[lon0, lat0], [lon0 + d, lat0], [lon0 + d, lat0 + d], [lon0, lat0 + d], [lon0, lat0]],
+                    [[a, c], [a, e], [b, e], [b, c], [a, c]],
+                ]),
+            },
+            properties: std::collections::HashMap::new(),
+            layer: "areas".to_string(),
+        }],
+    };
+
+    let tile_coord = LatLon::new(lat0, lon0).to_tile_coord(18);
+    let extent = Extent::default();
+    let encoded = json_to_mvt(&original, extent, tile_coord).unwrap();
+    let decoded = mvt_to_json(&encoded, None, CoordFormat::default()).unwrap();
+
+    assert_eq!(decoded.features.len(), 1);
+    let feature = &decoded.features[0];
+    assert_eq!(feature.geometry.type_, "Polygon", "hole must stay nested, not become its own polygon");
+    let rings = feature.geometry.coordinates.as_array().unwrap();
+    assert_eq!(rings.len(), 2);
+}